@@ -0,0 +1,85 @@
+use undo_stack::{UndoStack, Undoable};
+
+// Exercises the position-cursor timeline (goto/undo_n/redo_n) across a group boundary,
+// since a group occupies more than one timeline slot but must still count as a single step.
+fn main() {
+    let mut undo_stack = UndoStack::<UndoValue>::new(true);
+    let mut proj = Project { a: 0 };
+
+    undo_stack.push(UndoValue::ValueA(proj.a));
+    proj.a = 1; // entry 1
+    println!("{:?}", proj);
+
+    // A group of several pushes still counts as a single entry once closed.
+    undo_stack.start_group();
+    undo_stack.push(UndoValue::ValueA(proj.a));
+    proj.a = 2;
+    undo_stack.push(UndoValue::ValueA(proj.a));
+    proj.a = 3;
+    undo_stack.push(UndoValue::ValueA(proj.a));
+    proj.a = 4;
+    undo_stack.push(UndoValue::ValueA(proj.a));
+    proj.a = 5;
+    undo_stack.finish_group(); // entry 2
+    println!("{:?}", proj);
+
+    undo_stack.push(UndoValue::ValueA(proj.a));
+    proj.a = 6; // entry 3
+    println!("{:?}", proj);
+
+    assert_eq!(undo_stack.len(), 3);
+    assert_eq!(proj.a, 6);
+
+    // goto() should land exactly on entry boundaries, walking the group as one atomic step.
+    println!("\nJumping to entry 1...");
+    undo_stack.goto(1, &mut proj);
+    println!("{:?}", proj);
+    assert_eq!(undo_stack.len(), 1);
+    assert_eq!(proj.a, 1);
+
+    println!("\nJumping back to entry 3...");
+    undo_stack.goto(3, &mut proj);
+    println!("{:?}", proj);
+    assert_eq!(undo_stack.len(), 3);
+    assert_eq!(proj.a, 6);
+
+    // undo_n()/redo_n() count logical steps too, so 2 undos cross the whole group at once.
+    println!("\nUndoing 2 steps...");
+    undo_stack.undo_n(2, &mut proj);
+    println!("{:?}", proj);
+    assert_eq!(undo_stack.len(), 1);
+    assert_eq!(proj.a, 1);
+
+    println!("\nRedoing 2 steps...");
+    undo_stack.redo_n(2, &mut proj);
+    println!("{:?}", proj);
+    assert_eq!(undo_stack.len(), 3);
+    assert_eq!(proj.a, 6);
+
+    println!("\nAll timeline checks passed.");
+}
+
+// Our project type that holds the main data.
+#[derive(Debug, Clone, PartialEq)]
+struct Project {
+    a: i32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum UndoValue {
+    ValueA(i32),
+}
+
+impl Undoable for UndoValue {
+    type ProjectType = Project;
+
+    fn restore(self, proj: &mut Self::ProjectType) -> Self {
+        match self {
+            UndoValue::ValueA(value) => {
+                let replaced = proj.a;
+                proj.a = value;
+                UndoValue::ValueA(replaced)
+            }
+        }
+    }
+}
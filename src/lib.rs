@@ -16,6 +16,12 @@ enum Kind<T> {
     Single(T),
 }
 
+/// Opaque marker returned by [`UndoStack::snapshot`], used to later `rollback_to()` or `commit()`.
+pub struct Snapshot {
+    position: usize,
+    open_group: bool,
+}
+
 /// When calling undo() or redo(), the restore() function is always called and applies
 /// this value TO the project and returns a value with the previous state FROM the project.
 /// The "Undoable" type, usually an enum with many variants, needs to know how to restore itself,
@@ -26,18 +32,39 @@ where
 {
     type ProjectType;
     fn restore(self, target: &mut Self::ProjectType) -> Self;
+
+    /// Optionally combines this incoming value with `previous`, the value currently on
+    /// top of the undo stack, when both describe a change to the same logical target.
+    /// Returning `Some` lets `push` replace the top entry instead of appending a new one,
+    /// so runs of small edits (consecutive keystrokes, repeated nudges) collapse into a
+    /// single undo step. The returned value must still be the combined *undo* value (the
+    /// oldest pre-edit state), so a single undo reverts the whole coalesced run.
+    /// Defaults to `None`, so existing implementors see no behavior change.
+    fn merge(&self, _previous: &Self) -> Option<Self> {
+        None
+    }
 }
 
 /// The main struct where all the undo values are kept.
+///
+/// Internally this is a single flat timeline plus a `position` cursor pointing just past
+/// the last applied entry: `timeline[..position]` is the undo side, `timeline[position..]`
+/// is the redo side. This makes "jump to an arbitrary revision" (see `goto`) a matter of
+/// walking the cursor rather than shuffling values between two separate stacks. A `Kind::Group`
+/// span is always a single atomic step over that cursor, both when walked by `step` and when
+/// trimmed by `evict_to_limit`, so a still-open group is never partially evicted out from under
+/// itself and a closed group is never split across the `limit` boundary.
 pub struct UndoStack<T>
 where
     T: Undoable,
 {
-    future_stack: Vec<Kind<T>>,
-    past_stack: Vec<Kind<T>>,
+    timeline: Vec<Kind<T>>,
+    position: usize,
     undo_buffer: Option<T>,
     // Only a single open group is allowed at a time. That's why it's a single boolean, not a variant of "Kind".
     open_group: bool,
+    // Maximum number of entries kept on the undo side. `None` means unbounded.
+    limit: Option<usize>,
     /// Controls whether warning messages are printed or not. True by default.
     /// Only works if feature "std" is enabled.
     pub verbose: bool,
@@ -50,30 +77,133 @@ where
     /// Creates a new, empty Undo stack.
     pub fn new(verbose: bool) -> Self {
         Self {
-            future_stack: vec![],
-            past_stack: vec![],
+            timeline: vec![],
+            position: 0,
             undo_buffer: None,
             open_group: false,
+            limit: None,
             verbose,
         }
     }
 
-    /// Push a discrete "Undoable" value to the undo stack. Automatically clears future_stack redo values.
-    /// Will do nothing if value matches the value on top of undo stack.
+    /// Creates a new, empty Undo stack with a cap on how many entries the undo side may hold.
+    /// Once the limit is reached, the oldest entries are evicted to make room for new ones.
+    /// Useful for long-running or embedded/`no_std` uses where the history should not grow
+    /// without bound over a session.
+    pub fn with_limit(verbose: bool, limit: Option<usize>) -> Self {
+        let mut stack = Self::new(verbose);
+        stack.limit = limit;
+        stack
+    }
+
+    /// Sets (or clears) the entry limit, evicting oldest entries immediately if the
+    /// stack is already over the new limit; while a group is open, eviction waits
+    /// until `finish_group` closes it.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.evict_to_limit();
+    }
+
+    /// Returns the number of logical entries currently stored on the undo side, where a
+    /// whole group counts as a single entry regardless of how many values it holds.
+    pub fn len(&self) -> usize {
+        self.past_entry_count()
+    }
+
+    /// Returns the current capacity of the underlying timeline storage.
+    pub fn capacity(&self) -> usize {
+        self.timeline.capacity()
+    }
+
+    /// Counts logical entries in the undo side of the timeline (`self.timeline[..self.position]`):
+    /// a `Kind::Single` counts as one, and an entire `Kind::Group` span counts as one regardless
+    /// of how many values it holds. Assumes every group in range is fully closed, which holds
+    /// whenever `evict_to_limit` is allowed to run (it defers while a group is still open).
+    fn past_entry_count(&self) -> usize {
+        let mut count = 0;
+        let mut i = 0;
+        while i < self.position {
+            count += 1;
+            if matches!(self.timeline[i], Kind::Group) {
+                i += 1;
+                while i < self.position && !matches!(self.timeline[i], Kind::Group) {
+                    i += 1;
+                }
+            }
+            i += 1;
+        }
+        count
+    }
+
+    /// Drops entries from the front of the undo side until it is within `limit`, always
+    /// removing a partially-evicted group in its entirety so the group-pairing invariant
+    /// relied on by `step` is never left corrupted; a whole group counts as a single entry
+    /// toward `limit`, so it is evicted or kept as one unit, never split. Does nothing while
+    /// a group is still open: the oldest entry can be that group's own start marker, and
+    /// evicting it would delete the in-progress group out from under itself, leaving its
+    /// later `finish_group` to push an unpaired closing marker. `finish_group` re-checks the
+    /// limit right after closing the group, so eviction is simply deferred until then.
+    fn evict_to_limit(&mut self) {
+        if self.open_group {
+            return;
+        }
+        let Some(limit) = self.limit else { return };
+        while self.past_entry_count() > limit {
+            match self.timeline.first() {
+                Some(Kind::Group) => {
+                    self.timeline.remove(0);
+                    self.position -= 1;
+                    loop {
+                        match self.timeline.first() {
+                            Some(Kind::Group) => {
+                                self.timeline.remove(0);
+                                self.position -= 1;
+                                break;
+                            }
+                            Some(Kind::Single(_)) => {
+                                self.timeline.remove(0);
+                                self.position -= 1;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                Some(Kind::Single(_)) => {
+                    self.timeline.remove(0);
+                    self.position -= 1;
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Push a discrete "Undoable" value to the undo stack. Truncates any redo values ahead
+    /// of the cursor. Will do nothing if value matches the value on top of the undo side;
+    /// if it doesn't match but `Undoable::merge` says the two can be coalesced, replaces
+    /// the top entry instead of appending a new one.
     pub fn push(&mut self, undo_value: T) {
-        if let Some(Kind::Single(top_value)) = self.past_stack.last() {
+        if let Some(Kind::Single(top_value)) = self.position.checked_sub(1).and_then(|i| self.timeline.get(i)) {
             if *top_value == undo_value {
                 return;
             }
+            if let Some(combined) = undo_value.merge(top_value) {
+                self.timeline.truncate(self.position);
+                self.timeline[self.position - 1] = Kind::Single(combined);
+                return;
+            }
         }
-        self.past_stack.push(Kind::Single(undo_value));
-        self.future_stack.clear();
+        self.timeline.truncate(self.position);
+        self.timeline.push(Kind::Single(undo_value));
+        self.position += 1;
+        self.evict_to_limit();
     }
 
     /// Starts a "group" with multiple undo values that can be undone simultaneously.
     pub fn start_group(&mut self) {
         if !self.open_group {
-            self.past_stack.push(Kind::Group);
+            self.timeline.truncate(self.position);
+            self.timeline.push(Kind::Group);
+            self.position += 1;
             self.open_group = true;
         } else {
             self.maybe_print("UndoStack: Warning, can't open new group before closing current one.");
@@ -83,8 +213,10 @@ where
     /// Finishes the previously started undo group.
     pub fn finish_group(&mut self) {
         if self.open_group {
-            self.past_stack.push(Kind::Group);
+            self.timeline.insert(self.position, Kind::Group);
+            self.position += 1;
             self.open_group = false;
+            self.evict_to_limit();
         } else {
             self.maybe_print("UndoStack: Warning, no open groups to close.");
         }
@@ -94,108 +226,188 @@ where
     /// **IF** that value represents a group end. This allows retroactively pushing additional
     /// single undo values to the group.
     pub fn reopen_group(&mut self) {
-        if let Some(kind) = self.past_stack.pop(){
-            match kind {
-                Kind::Group => {
-                    if self.open_group {
-                        self.maybe_print("UndoStack: Warning, last value is already an open group. Skipping.");
-                    } else {
-                        self.open_group = true;
-                    }
-                },
-                Kind::Single(value) => {
-                    self.maybe_print("UndoStack: Warning, last value is not a group. Skipping.");
-                    self.past_stack.push(Kind::Single(value));
-                },
+        if self.position == 0 {
+            return;
+        }
+        let idx = self.position - 1;
+        if matches!(self.timeline[idx], Kind::Group) {
+            if self.open_group {
+                self.maybe_print("UndoStack: Warning, last value is already an open group. Skipping.");
+            } else {
+                self.timeline.remove(idx);
+                self.position -= 1;
+                self.open_group = true;
             }
+        } else {
+            self.maybe_print("UndoStack: Warning, last value is not a group. Skipping.");
         }
     }
 
     /// Performs undo, which will call the "restore" method on the restored value.
     /// Returns an option with the undone value for convenience.
     pub fn undo(&mut self, project: &mut T::ProjectType) -> Option<&T> {
-        self.move_undo_value(project, false)
+        self.step(project, false)
     }
 
     /// Performs redo, which will call the "restore" method on the restored value.
     /// Returns an option with the redone value for convenience.
     pub fn redo(&mut self, project: &mut T::ProjectType) -> Option<&T> {
-        self.move_undo_value(project, true)
+        self.step(project, true)
+    }
+
+    /// Performs `count` consecutive undo steps, applying `restore` along the way.
+    /// Stops early if the start of the timeline is reached.
+    pub fn undo_n(&mut self, count: usize, project: &mut T::ProjectType) -> Option<&T> {
+        for _ in 0..count {
+            if self.position == 0 {
+                break;
+            }
+            self.step(project, false);
+        }
+        self.current_marker(false)
+    }
+
+    /// Performs `count` consecutive redo steps, applying `restore` along the way.
+    /// Stops early if the end of the timeline is reached.
+    pub fn redo_n(&mut self, count: usize, project: &mut T::ProjectType) -> Option<&T> {
+        for _ in 0..count {
+            if self.position >= self.timeline.len() {
+                break;
+            }
+            self.step(project, true);
+        }
+        self.current_marker(true)
+    }
+
+    /// Walks the cursor directly to `index`, applying each intermediate `restore` in order.
+    /// `index` lives in the same space as `UndoStack::len()`: the number of logical
+    /// entries considered "past" once the jump completes. Groups are walked as a single
+    /// atomic step each, same as `undo`/`redo`, so `index` should land on a group boundary
+    /// rather than inside one.
+    pub fn goto(&mut self, index: usize, project: &mut T::ProjectType) {
+        while self.past_entry_count() > index {
+            self.step(project, false);
+        }
+        while self.past_entry_count() < index {
+            if self.position >= self.timeline.len() {
+                break;
+            }
+            self.step(project, true);
+        }
     }
 
-    /// The internal undo workhorse: moves the values to/from the appropriate stack.
+    /// The internal undo/redo workhorse: moves the cursor one entry (or one whole group)
+    /// in the given direction, calling `restore` along the way.
     /// Returns an option with the top value being moved.
-    fn move_undo_value(&mut self, project: &mut T::ProjectType, is_redo: bool) -> Option<&T> {
-        // Set appropriate stacks, depending on "undo" or "redo"
-        let from_stack: &mut Vec<Kind<T>>;
-        let to_stack: &mut Vec<Kind<T>>;
+    fn step(&mut self, project: &mut T::ProjectType, is_redo: bool) -> Option<&T> {
         if is_redo {
-            from_stack = &mut self.future_stack;
-            to_stack = &mut self.past_stack;
-        } else {
-            from_stack = &mut self.past_stack;
-            to_stack = &mut self.future_stack;
-        };
-
-        // Process undo value and location
-        match from_stack.pop() {
-            Some(kind) => match kind {
-                Kind::Group => {
-                    to_stack.push(Kind::Group);
-                    loop {
-                        let next_value = from_stack.pop();
-                        match next_value {
-                            Some(Kind::Group) => {
-                                to_stack.push(Kind::Group);
-                                break;
-                            }
-                            Some(Kind::Single(value)) => {
-                                let old_value = value.restore(project);
-                                to_stack.push(Kind::Single(old_value));
-                            }
-                            None => {
-                                #[cfg(feature = "std")]{
-                                    println!("UndoStack: Warning, Undo failure due to incomplete Undo Group");
-                                }
-                                break;
-                            }
-                        }
+            if self.position >= self.timeline.len() {
+                self.maybe_print("UndoStack: No value to undo/redo.");
+                return None;
+            }
+            if matches!(self.timeline[self.position], Kind::Group) {
+                self.position += 1;
+                loop {
+                    if self.position >= self.timeline.len() {
+                        self.maybe_print("UndoStack: Warning, Undo failure due to incomplete Undo Group");
+                        break;
                     }
+                    if matches!(self.timeline[self.position], Kind::Group) {
+                        self.position += 1;
+                        break;
+                    }
+                    self.restore_at(self.position, project);
+                    self.position += 1;
                 }
-                Kind::Single(value) => {
-                    let old_value = value.restore(project);
-                    to_stack.push(Kind::Single(old_value));
-                }
-            },
-            None => {
-                #[cfg(feature = "std")]{
-                    if self.verbose {
-                        println!("UndoStack: No value to undo/redo.");
+            } else {
+                self.restore_at(self.position, project);
+                self.position += 1;
+            }
+        } else {
+            if self.position == 0 {
+                self.maybe_print("UndoStack: No value to undo/redo.");
+                return None;
+            }
+            if matches!(self.timeline[self.position - 1], Kind::Group) {
+                self.position -= 1;
+                loop {
+                    if self.position == 0 {
+                        self.maybe_print("UndoStack: Warning, Undo failure due to incomplete Undo Group");
+                        break;
+                    }
+                    if matches!(self.timeline[self.position - 1], Kind::Group) {
+                        self.position -= 1;
+                        break;
                     }
+                    self.position -= 1;
+                    self.restore_at(self.position, project);
                 }
+            } else {
+                self.position -= 1;
+                self.restore_at(self.position, project);
             }
         }
-        //Return an option with whatever is at the top of the stack
-        to_stack.last().map_or_else(
-            || None,
-            |kind| match kind {
-                Kind::Group => None,
-                Kind::Single(value) => Some(value),
-            },
-        )
+        self.current_marker(is_redo)
+    }
+
+    /// Applies `restore` to the `Kind::Single` entry at `idx` in place, swapping in the
+    /// value it returns (the previous project state) so the entry is ready to be walked
+    /// the other way next time.
+    fn restore_at(&mut self, idx: usize, project: &mut T::ProjectType) {
+        let slot = &mut self.timeline[idx];
+        if let Kind::Single(value) = core::mem::replace(slot, Kind::Group) {
+            *slot = Kind::Single(value.restore(project));
+        }
     }
 
+    /// Returns the entry the cursor is now resting next to, mirroring what the old
+    /// two-stack implementation returned by peeking the destination stack: `None` whenever
+    /// that entry is a group marker rather than a single value.
+    fn current_marker(&self, is_redo: bool) -> Option<&T> {
+        let idx = if is_redo { self.position.checked_sub(1) } else { Some(self.position) };
+        idx.and_then(|i| self.timeline.get(i)).and_then(|kind| match kind {
+            Kind::Group => None,
+            Kind::Single(value) => Some(value),
+        })
+    }
+
+    /// Captures the current cursor position and group state as an opaque token, to later
+    /// pass to `rollback_to()` or `commit()`.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            position: self.position,
+            open_group: self.open_group,
+        }
+    }
+
+    /// Reverts the project back to the state recorded in `snap`, calling `restore` on every
+    /// entry pushed since then. Unlike `undo()`, those entries are not kept for a later
+    /// `redo()`; they're removed outright, along with anything already on the redo side.
+    pub fn rollback_to(&mut self, snap: Snapshot, project: &mut T::ProjectType) {
+        while self.position > snap.position {
+            self.position -= 1;
+            if let Kind::Single(value) = core::mem::replace(&mut self.timeline[self.position], Kind::Group) {
+                let _replaced = value.restore(project);
+            }
+        }
+        self.timeline.truncate(snap.position);
+        self.open_group = snap.open_group;
+    }
+
+    /// Drops a snapshot token, keeping every entry pushed since it was taken.
+    pub fn commit(&mut self, _snap: Snapshot) {}
+
     /// Completely empties the undo and redo stacks and the temporary buffer.
     pub fn clear(&mut self) {
         self.undo_buffer = None;
-        self.past_stack.clear();
-        self.future_stack.clear();
+        self.timeline.clear();
+        self.position = 0;
         self.open_group = false;
     }
 
     /// Returns true if both the undo and redo stacks are empty.
     pub fn is_empty(&self) -> bool {
-        self.past_stack.is_empty() && self.future_stack.is_empty()
+        self.timeline.is_empty()
     }
 
     /// Returns true is the temporary buffer is empty.
@@ -242,97 +454,6 @@ where
     }
 
 
-    // /// EXPERIMENTAL: Allows manipulating the last existing undo value into a group start,
-    // /// retroactively grouping undo values together.
-    // pub fn set_last_to_group_start(&mut self) {
-    //     if let Some(kind) = self.past_stack.pop(){
-    //         match kind {
-    //             Kind::Group => {
-    //                 if !self.open_group {
-    //                     self.open_group = true;
-    //                 } else {
-    //                     #[cfg(feature = "std")]{
-    //                         if self.verbose {
-    //                             println!("UndoStack: Warning, last value is already a group start. Skipping.");
-    //                         }
-    //                     }
-    //                 }
-    //             },
-    //             Kind::Single(value) => {
-    //                 self.start_group();
-    //                 self.past_stack.push(Kind::Single(value));
-    //             },
-    //         }
-    //     }
-    // }
-
-    // /// EXPERIMENTAL: Allows manipulating the last existing undo value into a group end.
-    // pub fn set_last_to_group_end(&mut self) {
-    //     if let Some(kind) = self.past_stack.pop(){
-    //         match kind {
-    //             Kind::Group => {
-    //                 if !self.open_group {
-    //                     #[cfg(feature = "std")]{
-    //                         if self.verbose {
-    //                             println!("UndoStack: Warning, last value is already a group end. Skipping.");
-    //                         }
-    //                     }
-    //                 } else {
-    //                     self.open_group = false;
-    //                 }
-    //             },
-    //             Kind::Single(value) => {
-    //                 self.past_stack.push(Kind::Single(value));
-    //                 self.finish_group();
-    //             },
-    //         }
-    //     }
-    // }
-
-
-    // /// Pops the top value in the undo stack (past_stack) and returns it as an option.
-    // pub fn pop_undo(&mut self) -> Option<T> {
-    //     self.past_stack.pop().map_or_else(
-    //         || None,
-    //         |kind| {
-    //             match kind {
-    //                 Kind::Group => None,
-    //                 Kind::Single(value) => Some(value),
-    //             }
-    //         }
-    //     )
-    // }
-
-    // /// Pops the top value in the redo stack (future_stack) and returns it  as an option.
-    // pub fn pop_redo(&mut self) -> Option<T> {
-    //     self.future_stack.pop().map_or_else(
-    //         || None,
-    //         |kind| {
-    //             match kind {
-    //                 Kind::Group => None,
-    //                 Kind::Single(value) => Some(value),
-    //             }
-    //         }
-    //     )
-    // }
-
-    // /// Returns an immutable reference to the past_stack vector (undo stack).
-    // pub fn past_stack(&self) -> &Vec<T> { &self.past_stack }
-
-    // /// Returns an immutable reference to the future_stack vector (redo stack).
-    // pub fn future_stack(&self) -> &Vec<T> { &self.future_stack }
-
-    // /// Checks if a new undo value is different from the one currently at the top of the stack.
-    // /// Can be used to prevent pushing redundant values. In some cases, redundant values can be useful,
-    // /// so this check is not performed by default.
-    // pub fn value_is_different(&self, undo_value:T) -> bool {
-    //     if let Some(kind) = self.past_stack.last() {
-    //         if let Kind::Single(top_value) = kind {
-    //             return *top_value != undo_value
-    //         }
-    //     }
-    //     false
-    // }
 }
 
 impl<T> Default for UndoStack<T>
@@ -343,3 +464,93 @@ where
         Self::new(false)
     }
 }
+
+/// Returned by [`UndoManager`] operations that need an active stack when none has been set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoActiveStack;
+
+/// Owns several named [`UndoStack`] instances, at most one of which is "active" at a time.
+/// `push`/`undo`/`redo` are forwarded to whichever stack is currently active, so each
+/// document (e.g. one per open tab) keeps its own independent history.
+pub struct UndoManager<Id, T>
+where
+    Id: Clone + PartialEq,
+    T: Undoable,
+{
+    stacks: Vec<(Id, UndoStack<T>)>,
+    active: Option<Id>,
+}
+
+impl<Id, T> UndoManager<Id, T>
+where
+    Id: Clone + PartialEq,
+    T: Undoable,
+{
+    /// Creates a new, empty manager with no stacks and no active document.
+    pub fn new() -> Self {
+        Self {
+            stacks: vec![],
+            active: None,
+        }
+    }
+
+    /// Adds a new, empty undo stack under `id`. Returns the same `id` back as a handle,
+    /// for convenience when the caller wants to immediately `set_active` it.
+    pub fn add(&mut self, id: Id, verbose: bool) -> Id {
+        self.stacks.push((id.clone(), UndoStack::new(verbose)));
+        id
+    }
+
+    /// Removes the stack with the given `id`, if any. Clears the active slot if it pointed at it.
+    pub fn remove(&mut self, id: &Id) {
+        self.stacks.retain(|(existing, _)| existing != id);
+        if self.active.as_ref() == Some(id) {
+            self.active = None;
+        }
+    }
+
+    /// Marks the stack with the given `id` as active. Does nothing if no such stack exists.
+    pub fn set_active(&mut self, id: Id) {
+        if self.stacks.iter().any(|(existing, _)| *existing == id) {
+            self.active = Some(id);
+        }
+    }
+
+    /// Returns a reference to the currently active stack, if any.
+    pub fn active_stack(&self) -> Option<&UndoStack<T>> {
+        let id = self.active.as_ref()?;
+        self.stacks.iter().find(|(existing, _)| existing == id).map(|(_, stack)| stack)
+    }
+
+    /// Returns a mutable reference to the currently active stack, if any.
+    pub fn active_stack_mut(&mut self) -> Option<&mut UndoStack<T>> {
+        let id = self.active.as_ref()?;
+        self.stacks.iter_mut().find(|(existing, _)| existing == id).map(|(_, stack)| stack)
+    }
+
+    /// Pushes an undo value onto the active stack.
+    pub fn push(&mut self, value: T) -> Result<(), NoActiveStack> {
+        self.active_stack_mut().ok_or(NoActiveStack)?.push(value);
+        Ok(())
+    }
+
+    /// Performs undo on the active stack.
+    pub fn undo(&mut self, project: &mut T::ProjectType) -> Result<Option<&T>, NoActiveStack> {
+        Ok(self.active_stack_mut().ok_or(NoActiveStack)?.undo(project))
+    }
+
+    /// Performs redo on the active stack.
+    pub fn redo(&mut self, project: &mut T::ProjectType) -> Result<Option<&T>, NoActiveStack> {
+        Ok(self.active_stack_mut().ok_or(NoActiveStack)?.redo(project))
+    }
+}
+
+impl<Id, T> Default for UndoManager<Id, T>
+where
+    Id: Clone + PartialEq,
+    T: Undoable,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}